@@ -0,0 +1,81 @@
+// Copyright 2023 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use tracing::error;
+use utoipa::ToSchema;
+
+use crate::context::Context;
+use crate::services::webhook::WebhookService;
+
+/// The fields we need out of a GitHub-style push event; the rest of the
+/// payload is ignored.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PushEvent {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub repository: Repository,
+    pub after: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct Repository {
+    pub clone_url: String,
+}
+
+/// Receives a Git provider push event, verifies its HMAC signature against
+/// the secret configured in the `amp-configurations` ConfigMap, and forces
+/// every Playbook that references the pushed repo/branch back into
+/// `solving` so it picks up the new commit.
+#[utoipa::path(
+    post,
+    path = "/webhooks/push",
+    request_body = PushEvent,
+    responses(
+        (status = 202, description = "Push event accepted, matching playbooks re-queued for solving"),
+        (status = 400, description = "Malformed push event payload"),
+        (status = 401, description = "Missing or invalid webhook signature"),
+    ),
+    tag = "Webhooks",
+)]
+pub async fn push(State(ctx): State<Arc<Context>>, headers: HeaderMap, body: Bytes) -> Result<StatusCode, StatusCode> {
+    let signature = headers
+        .get("X-Hub-Signature-256")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if !WebhookService::verify(&ctx.config.webhook_secret, &body, signature) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let event: PushEvent = serde_json::from_slice(&body).map_err(|e| {
+        error!("Failed to parse push event: {:?}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let branch = event.reference.trim_start_matches("refs/heads/");
+    WebhookService::handle_push(ctx, &event.repository.clone_url, branch, &event.after)
+        .await
+        .map_err(|e| {
+            error!("Failed to handle push event: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(StatusCode::ACCEPTED)
+}
@@ -0,0 +1,30 @@
+// Copyright 2023 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use axum::routing::post;
+use axum::Router;
+
+use crate::context::Context;
+use crate::{handlers, swagger};
+
+/// Builds the apiserver's top-level router: the `/webhooks/push` handler
+/// merged with the `/swagger` UI and `/openapi.json` document.
+pub fn build(ctx: Arc<Context>) -> Router {
+    Router::new()
+        .route("/webhooks/push", post(handlers::webhook::push))
+        .with_state(ctx)
+        .merge(swagger::build())
+}
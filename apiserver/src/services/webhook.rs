@@ -0,0 +1,150 @@
+// Copyright 2023 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use amp_common::resource::PlaybookSpec;
+use amp_resources::playbook::{self, PlaybookState};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sha2::Sha256;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::context::Context;
+use crate::errors::ApiError;
+use crate::services::Result;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How long a delivery's commit SHA is remembered. Git providers retry
+/// webhook deliveries on timeout, so without this a single push can
+/// thrash the controller with several redundant re-solves.
+const DEDUPE_WINDOW: Duration = Duration::from_secs(60);
+
+static SEEN: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub struct WebhookService;
+
+impl WebhookService {
+    /// Verifies `signature` (a `sha256=<hex>` header value) against `body`
+    /// using the shared secret from the `amp-configurations` ConfigMap, the
+    /// same HMAC-SHA256 scheme GitHub signs its deliveries with.
+    pub fn verify(secret: &str, body: &[u8], signature: &str) -> bool {
+        let Some(expected_hex) = signature.strip_prefix("sha256=") else {
+            return false;
+        };
+
+        let Ok(mut mac) = HmacSha256::new_from_slice(secret.as_bytes()) else {
+            return false;
+        };
+        mac.update(body);
+
+        match hex::decode(expected_hex) {
+            Ok(expected) => mac.verify_slice(&expected).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    /// Forces every Playbook with an actor on `repo` at `branch`, or with an
+    /// actor listing `repo` as a partner (matched on the repo alone, since
+    /// partners name no branch), back into `PlaybookState::solving()`,
+    /// skipping deliveries already handled for `commit` within the dedupe
+    /// window. Returns the
+    /// number of playbooks that were re-queued for a re-solve.
+    pub async fn handle_push(ctx: Arc<Context>, repo: &str, branch: &str, commit: &str) -> Result<usize> {
+        if Self::seen_recently(commit).await {
+            debug!("Ignoring duplicate webhook delivery for commit {}", commit);
+            return Ok(0);
+        }
+
+        let playbooks = playbook::list(&ctx.k8s)
+            .await
+            .map_err(|err| ApiError::KubernetesError(err.to_string()))?;
+
+        let mut affected = 0;
+        for resource in playbooks {
+            if !references(&resource.spec, repo, branch) {
+                continue;
+            }
+
+            playbook::patch_status(ctx.k8s.clone(), &resource, PlaybookState::solving())
+                .await
+                .map_err(|err| ApiError::KubernetesError(err.to_string()))?;
+            affected += 1;
+        }
+
+        if affected == 0 {
+            warn!("Webhook push for {}@{} matched no playbooks", repo, branch);
+        }
+
+        Ok(affected)
+    }
+
+    async fn seen_recently(commit: &str) -> bool {
+        let mut seen = SEEN.lock().await;
+        seen.retain(|_, at| at.elapsed() < DEDUPE_WINDOW);
+
+        if seen.contains_key(commit) {
+            return true;
+        }
+
+        seen.insert(commit.to_string(), Instant::now());
+        false
+    }
+}
+
+fn references(spec: &PlaybookSpec, repo: &str, branch: &str) -> bool {
+    spec.actors.iter().any(|actor| {
+        (actor.repo == repo && actor.reference == branch) || actor.partners.iter().any(|partner| partner == repo)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn verify_accepts_a_correctly_signed_body() {
+        let body = b"{\"ref\":\"refs/heads/main\"}";
+        let signature = sign("shh", body);
+        assert!(WebhookService::verify("shh", body, &signature));
+    }
+
+    #[test]
+    fn verify_rejects_a_signature_from_the_wrong_secret() {
+        let body = b"{\"ref\":\"refs/heads/main\"}";
+        let signature = sign("other-secret", body);
+        assert!(!WebhookService::verify("shh", body, &signature));
+    }
+
+    #[test]
+    fn verify_rejects_a_tampered_body() {
+        let signature = sign("shh", b"original");
+        assert!(!WebhookService::verify("shh", b"tampered", &signature));
+    }
+
+    #[test]
+    fn verify_rejects_a_missing_scheme_prefix() {
+        assert!(!WebhookService::verify("shh", b"body", "deadbeef"));
+    }
+}
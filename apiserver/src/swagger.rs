@@ -37,6 +37,8 @@ use crate::{handlers, requests};
         handlers::playbook::stop,
         handlers::playbook::events,
         handlers::actor::list,
+        //
+        handlers::webhook::push,
     ),
     components(
         schemas(
@@ -59,12 +61,15 @@ use crate::{handlers, requests};
             schema::Port,
             schema::RegisteredPartner,
             schema::Service,
-
+            //
+            handlers::webhook::PushEvent,
+            handlers::webhook::Repository,
         )
     ),
     tags(
         (name = "Actors", description = "The Actors Service Handlers"),
         (name = "Playbooks", description = "The Playbooks Service Handlers"),
+        (name = "Webhooks", description = "Git provider push webhooks"),
     ),
 )]
 struct ApiDoc;
@@ -51,5 +51,34 @@ pub async fn new(ctx: &Arc<Context>) {
 // This function lets the app handle an added/modified configmap from k8s.
 fn handle_config_map(_ctx: &Arc<Context>, cm: &ConfigMap) -> Result<()> {
     debug!("Handle an added/modified configmap from k8s: {:#?}", cm.data);
+
+    if let Some(data) = &cm.data {
+        apply_cache_config(data);
+    }
+
     Ok(())
 }
+
+/// Applies a live manifest-cache backend change from the
+/// `amp-configurations` ConfigMap, so switching backends doesn't require
+/// restarting the controllers.
+fn apply_cache_config(data: &std::collections::BTreeMap<String, String>) {
+    let backend = data.get("cache.backend").map(String::as_str).unwrap_or("in-memory");
+
+    let config = match backend {
+        "in-memory" => amp_resolver::cache::CacheBackendConfig::InMemory,
+        "redis" => match data.get("cache.redis.url") {
+            Some(url) => amp_resolver::cache::CacheBackendConfig::Redis { url: url.clone() },
+            None => {
+                error!("cache.backend=redis but cache.redis.url is not set; keeping the current cache");
+                return;
+            }
+        },
+        other => {
+            error!("Unknown cache.backend \"{}\"; keeping the current cache", other);
+            return;
+        }
+    };
+
+    amp_resolver::cache::configure(&config);
+}
@@ -0,0 +1,222 @@
+// Copyright 2023 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use amp_common::resource::CharacterSpec;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+
+/// TTL applied to manifests resolved from an unpinned ref (e.g. `master`),
+/// short enough that a fresh push is picked up without waiting too long.
+pub const UNPINNED_TTL: Duration = Duration::from_secs(30);
+
+/// Manifests resolved from an immutable commit SHA never go stale, so they
+/// can be cached aggressively.
+pub const PINNED_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Key a resolved manifest is cached under: a partner's repo/name plus the
+/// exact commit it resolved to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub repo: String,
+    pub commit: String,
+}
+
+impl CacheKey {
+    pub fn new(repo: impl Into<String>, commit: impl Into<String>) -> Self {
+        Self {
+            repo: repo.into(),
+            commit: commit.into(),
+        }
+    }
+}
+
+/// A cache of resolved [`CharacterSpec`]s, consulted by `load()` before
+/// falling back to a catalog/hub/source fetch.
+#[async_trait]
+pub trait ManifestCache: Send + Sync {
+    async fn get(&self, key: &CacheKey) -> Option<CharacterSpec>;
+    async fn put(&self, key: CacheKey, spec: CharacterSpec, ttl: Duration);
+}
+
+struct Entry {
+    spec: CharacterSpec,
+    expires_at: Instant,
+}
+
+/// Default, process-local [`ManifestCache`] backed by a `HashMap`.
+#[derive(Default)]
+pub struct InMemoryManifestCache {
+    entries: RwLock<HashMap<CacheKey, Entry>>,
+}
+
+#[async_trait]
+impl ManifestCache for InMemoryManifestCache {
+    async fn get(&self, key: &CacheKey) -> Option<CharacterSpec> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(key)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .map(|entry| entry.spec.clone())
+    }
+
+    async fn put(&self, key: CacheKey, spec: CharacterSpec, ttl: Duration) {
+        let mut entries = self.entries.write().unwrap();
+        entries.insert(
+            key,
+            Entry {
+                spec,
+                expires_at: Instant::now() + ttl,
+            },
+        );
+    }
+}
+
+/// Redis-backed [`ManifestCache`], selected when the `amp-configurations`
+/// ConfigMap configures a `redis` cache backend. Entries are stored as
+/// JSON; the TTL is delegated to Redis's own key expiry.
+pub struct RedisManifestCache {
+    client: redis::Client,
+}
+
+impl RedisManifestCache {
+    pub fn new(url: &str) -> redis::RedisResult<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+        })
+    }
+
+    fn redis_key(key: &CacheKey) -> String {
+        format!("amphitheatre:manifest:{}@{}", key.repo, key.commit)
+    }
+}
+
+#[async_trait]
+impl ManifestCache for RedisManifestCache {
+    async fn get(&self, key: &CacheKey) -> Option<CharacterSpec> {
+        let mut conn = self.client.get_async_connection().await.ok()?;
+        let raw: Option<String> = redis::AsyncCommands::get(&mut conn, Self::redis_key(key)).await.ok()?;
+        raw.and_then(|value| serde_json::from_str(&value).ok())
+    }
+
+    async fn put(&self, key: CacheKey, spec: CharacterSpec, ttl: Duration) {
+        let Ok(mut conn) = self.client.get_async_connection().await else {
+            return;
+        };
+        let Ok(value) = serde_json::to_string(&spec) else {
+            return;
+        };
+        let _: redis::RedisResult<()> =
+            redis::AsyncCommands::set_ex(&mut conn, Self::redis_key(&key), value, ttl.as_secs()).await;
+    }
+}
+
+/// Selects which [`ManifestCache`] backend is active, set from the
+/// `amp-configurations` ConfigMap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CacheBackendConfig {
+    InMemory,
+    Redis { url: String },
+}
+
+static ACTIVE: Lazy<RwLock<Arc<dyn ManifestCache>>> = Lazy::new(|| RwLock::new(Arc::new(InMemoryManifestCache::default())));
+static ACTIVE_CONFIG: Lazy<RwLock<CacheBackendConfig>> = Lazy::new(|| RwLock::new(CacheBackendConfig::InMemory));
+
+/// Returns the currently active cache.
+pub fn active() -> Arc<dyn ManifestCache> {
+    ACTIVE.read().unwrap().clone()
+}
+
+/// Rebuilds and installs the active cache for `config`, replacing whatever
+/// backend was previously active. Called live as the `amp-configurations`
+/// ConfigMap changes. A Redis URL that can't be opened leaves the currently
+/// active cache in place rather than taking manifest resolution down.
+///
+/// A no-op when `config` matches the backend that's already active: most
+/// watched ConfigMap events (an unrelated field edit, a periodic relist
+/// with no changes at all) don't touch `cache.*`, and rebuilding a fresh
+/// `InMemoryManifestCache` on every one of those would silently discard
+/// every pinned commit's cache entry, defeating the point of caching them.
+pub fn configure(config: &CacheBackendConfig) {
+    if *ACTIVE_CONFIG.read().unwrap() == *config {
+        return;
+    }
+
+    let cache: Arc<dyn ManifestCache> = match config {
+        CacheBackendConfig::InMemory => Arc::new(InMemoryManifestCache::default()),
+        CacheBackendConfig::Redis { url } => match RedisManifestCache::new(url) {
+            Ok(cache) => Arc::new(cache),
+            Err(e) => {
+                tracing::error!("Failed to configure redis manifest cache: {:?}; keeping the current cache", e);
+                return;
+            }
+        },
+    };
+
+    *ACTIVE.write().unwrap() = cache;
+    *ACTIVE_CONFIG.write().unwrap() = config.clone();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> CharacterSpec {
+        serde_json::from_value(serde_json::json!({"name": "web", "version": "1.0.0", "commit": "deadbeef"}))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_misses_on_an_empty_cache() {
+        let cache = InMemoryManifestCache::default();
+        assert!(cache.get(&CacheKey::new("example/repo", "deadbeef")).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn put_then_get_returns_the_cached_spec() {
+        let cache = InMemoryManifestCache::default();
+        let key = CacheKey::new("example/repo", "deadbeef");
+        cache.put(key.clone(), spec(), Duration::from_secs(60)).await;
+
+        assert!(cache.get(&key).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn an_expired_entry_is_not_returned() {
+        let cache = InMemoryManifestCache::default();
+        let key = CacheKey::new("example/repo", "deadbeef");
+        cache.put(key.clone(), spec(), Duration::from_millis(0)).await;
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(cache.get(&key).await.is_none());
+    }
+
+    #[test]
+    fn cache_key_equality_is_by_repo_and_commit() {
+        assert_eq!(CacheKey::new("a", "1"), CacheKey::new("a", "1"));
+        assert_ne!(CacheKey::new("a", "1"), CacheKey::new("a", "2"));
+    }
+
+    #[test]
+    fn configure_is_a_no_op_when_the_backend_is_unchanged() {
+        let before = active();
+        configure(&CacheBackendConfig::InMemory);
+        let after = active();
+
+        assert!(Arc::ptr_eq(&before, &after));
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2023 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, ResolveError>;
+
+#[derive(Debug, Error)]
+pub enum ResolveError {
+    #[error("partner kind is not supported by this resolver")]
+    UnsupportedPartner,
+
+    #[error("unknown character registry: {0}")]
+    UnknownCharacterRegistry(String),
+
+    /// The resolved manifest's content digest no longer matches the one
+    /// pinned in the Playbook's lockfile, and `relock` wasn't requested.
+    #[error("resolved manifest for \"{0}\" no longer matches its locked digest")]
+    IntegrityMismatch(String),
+
+    /// Raised when a resolved [`amp_common::resource::CharacterSpec`]
+    /// can't be canonicalized into the lockfile's JSON representation.
+    #[error("failed to serialize manifest for lockfile digest: {0}")]
+    LockSerializationError(String),
+}
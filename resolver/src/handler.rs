@@ -0,0 +1,143 @@
+// Copyright 2023 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use amp_common::config::Credentials;
+use amp_common::resource::CharacterSpec;
+use async_trait::async_trait;
+use kube::Client as KubeClient;
+use once_cell::sync::Lazy;
+
+use crate::errors::{ResolveError, Result};
+use crate::{load_from_catalog, load_from_cluster};
+
+/// A backend capable of resolving a named character against one registry
+/// scheme, e.g. `catalog` or `hub`. Additional backends (an OCI-artifact
+/// registry, an HTTP index, ...) implement this trait and call
+/// [`register`] instead of editing the resolver's dispatch logic.
+#[async_trait]
+pub trait ResolverHandler: Send + Sync {
+    /// The `registry` value this handler answers for, e.g. `"catalog"`.
+    fn scheme(&self) -> &'static str;
+
+    async fn resolve(
+        &self, name: &str, version: &str, credentials: &Credentials, client: &KubeClient,
+    ) -> Result<CharacterSpec>;
+}
+
+struct CatalogHandler;
+
+#[async_trait]
+impl ResolverHandler for CatalogHandler {
+    fn scheme(&self) -> &'static str {
+        "catalog"
+    }
+
+    async fn resolve(
+        &self, name: &str, version: &str, credentials: &Credentials, _client: &KubeClient,
+    ) -> Result<CharacterSpec> {
+        load_from_catalog(credentials, name, version)
+    }
+}
+
+struct HubHandler;
+
+#[async_trait]
+impl ResolverHandler for HubHandler {
+    fn scheme(&self) -> &'static str {
+        "hub"
+    }
+
+    async fn resolve(
+        &self, name: &str, _version: &str, _credentials: &Credentials, client: &KubeClient,
+    ) -> Result<CharacterSpec> {
+        load_from_cluster(client, name).await
+    }
+}
+
+static HANDLERS: Lazy<RwLock<HashMap<&'static str, Arc<dyn ResolverHandler>>>> = Lazy::new(|| {
+    let mut handlers: HashMap<&'static str, Arc<dyn ResolverHandler>> = HashMap::new();
+    handlers.insert("catalog", Arc::new(CatalogHandler));
+    handlers.insert("hub", Arc::new(HubHandler));
+    RwLock::new(handlers)
+});
+
+/// Registers an additional [`ResolverHandler`] under its
+/// [`ResolverHandler::scheme`], making it dispatchable from `load()`
+/// without any change to the core resolver. Registering under an
+/// already-used scheme replaces the previous handler.
+pub fn register(handler: Arc<dyn ResolverHandler>) {
+    HANDLERS.write().unwrap().insert(handler.scheme(), handler);
+}
+
+/// Looks up the handler registered for `scheme`. The returned `Arc` is
+/// cloned out from under the registry lock so callers can safely `.await`
+/// the handler's `resolve()` without holding the lock across it.
+pub fn lookup(scheme: &str) -> Result<Arc<dyn ResolverHandler>> {
+    let handlers = HANDLERS.read().unwrap();
+    handlers.get(scheme).cloned().ok_or_else(|| {
+        let mut known: Vec<&str> = handlers.keys().copied().collect();
+        known.sort_unstable();
+        ResolveError::UnknownCharacterRegistry(format!("{scheme} (known registries: {})", known.join(", ")))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use amp_common::config::Credentials;
+
+    use super::*;
+
+    #[test]
+    fn lookup_finds_the_builtin_handlers() {
+        assert_eq!(lookup("catalog").unwrap().scheme(), "catalog");
+        assert_eq!(lookup("hub").unwrap().scheme(), "hub");
+    }
+
+    #[test]
+    fn lookup_lists_known_schemes_for_an_unknown_one() {
+        let err = lookup("does-not-exist").unwrap_err();
+        match err {
+            ResolveError::UnknownCharacterRegistry(message) => {
+                assert!(message.contains("does-not-exist"));
+                assert!(message.contains("catalog"));
+                assert!(message.contains("hub"));
+            }
+            other => panic!("expected UnknownCharacterRegistry, got {other:?}"),
+        }
+    }
+
+    struct EchoHandler;
+
+    #[async_trait]
+    impl ResolverHandler for EchoHandler {
+        fn scheme(&self) -> &'static str {
+            "echo-test-scheme"
+        }
+
+        async fn resolve(
+            &self, _name: &str, _version: &str, _credentials: &Credentials, _client: &KubeClient,
+        ) -> Result<CharacterSpec> {
+            unreachable!("not exercised by this test")
+        }
+    }
+
+    #[test]
+    fn register_makes_a_new_handler_dispatchable() {
+        register(Arc::new(EchoHandler));
+        assert_eq!(lookup("echo-test-scheme").unwrap().scheme(), "echo-test-scheme");
+    }
+}
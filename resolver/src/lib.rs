@@ -0,0 +1,50 @@
+// Copyright 2023 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub mod cache;
+pub mod errors;
+pub mod handler;
+pub mod lock;
+pub mod partner;
+
+use amp_common::config::Credentials;
+use amp_common::resource::CharacterSpec;
+use kube::Client as KubeClient;
+
+use errors::Result;
+
+/// Resolves a character manifest from the `catalog` registry by name and
+/// version.
+///
+/// Unimplemented: every call panics via `unimplemented!` rather than
+/// returning a manifest.
+pub(crate) fn load_from_catalog(_credentials: &Credentials, _name: &str, _version: &str) -> Result<CharacterSpec> {
+    unimplemented!("catalog registry lookup")
+}
+
+/// Resolves a character manifest already published as a CRD in this
+/// cluster, for the `hub` registry scheme.
+///
+/// Unimplemented: every call panics via `unimplemented!`.
+pub(crate) async fn load_from_cluster(_client: &KubeClient, _name: &str) -> Result<CharacterSpec> {
+    unimplemented!("in-cluster hub lookup")
+}
+
+/// Resolves a character manifest directly from a partner's git repository
+/// reference, for [`amp_common::resource::Partner::Repository`] partners.
+///
+/// Unimplemented: every call panics via `unimplemented!`.
+pub(crate) fn load_from_source(_credentials: &Credentials, _reference: &str) -> Result<CharacterSpec> {
+    unimplemented!("git source lookup")
+}
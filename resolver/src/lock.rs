@@ -0,0 +1,114 @@
+// Copyright 2023 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, HashMap};
+
+use amp_common::resource::CharacterSpec;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// A single pinned entry, recording the exact commit and content digest a
+/// partner resolved to so a later reconcile can detect drift instead of
+/// silently rebuilding against something different.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct LockEntry {
+    pub commit: String,
+    pub sha256: String,
+}
+
+/// Maps a partner key (`repo@reference`) to the [`LockEntry`] it last
+/// resolved to. Persisted on the Playbook status so it survives reconciles.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Lock {
+    pub entries: HashMap<String, LockEntry>,
+}
+
+impl Lock {
+    pub fn get(&self, key: &str) -> Option<&LockEntry> {
+        self.entries.get(key)
+    }
+
+    pub fn set(&mut self, key: impl Into<String>, entry: LockEntry) {
+        self.entries.insert(key.into(), entry);
+    }
+}
+
+/// Computes the content-integrity digest for a resolved manifest: a
+/// SHA-256 hash over its canonically serialized (key-sorted) JSON plus the
+/// git commit it was resolved against, so a moved `master` ref or a
+/// swapped catalog entry changes the digest even when the manifest text
+/// looks similar.
+pub fn digest(spec: &CharacterSpec, commit: &str) -> serde_json::Result<String> {
+    let canonical = serde_json::to_vec(&canonicalize(serde_json::to_value(spec)?))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(&canonical);
+    hasher.update(commit.as_bytes());
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Recursively sorts object keys so that two semantically equal manifests
+/// always serialize to the same bytes, regardless of field insertion order.
+fn canonicalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let sorted: BTreeMap<String, Value> = map.into_iter().map(|(k, v)| (k, canonicalize(v))).collect();
+            Value::Object(sorted.into_iter().collect())
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(canonicalize).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn canonicalize_sorts_object_keys_regardless_of_nesting() {
+        let value = json!({"b": 1, "a": {"d": 2, "c": 3}});
+        assert_eq!(canonicalize(value), json!({"a": {"c": 3, "d": 2}, "b": 1}));
+    }
+
+    #[test]
+    fn digest_is_stable_across_equivalent_field_order() {
+        let a = serde_json::from_value(json!({"name": "web", "version": "1.0.0", "commit": "abc"})).unwrap();
+        let b = serde_json::from_value(json!({"version": "1.0.0", "commit": "abc", "name": "web"})).unwrap();
+
+        assert_eq!(digest(&a, "deadbeef").unwrap(), digest(&b, "deadbeef").unwrap());
+    }
+
+    #[test]
+    fn digest_changes_with_the_commit() {
+        let spec: CharacterSpec = serde_json::from_value(json!({"name": "web", "version": "1.0.0"})).unwrap();
+        assert_ne!(digest(&spec, "aaa").unwrap(), digest(&spec, "bbb").unwrap());
+    }
+
+    #[test]
+    fn lock_roundtrips_entries_by_key() {
+        let mut lock = Lock::default();
+        let entry = LockEntry {
+            commit: "abc123".into(),
+            sha256: "deadbeef".into(),
+        };
+        lock.set("example/repo@master", entry.clone());
+
+        assert_eq!(lock.get("example/repo@master"), Some(&entry));
+        assert_eq!(lock.get("example/repo@other"), None);
+    }
+}
@@ -12,9 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::cache::CacheKey;
+use crate::lock::LockEntry;
 use crate::{
+    cache,
     errors::{ResolveError, Result},
-    load_from_catalog, load_from_cluster, load_from_source,
+    handler, load_from_source, lock,
 };
 use amp_common::{
     config::Credentials,
@@ -22,24 +25,93 @@ use amp_common::{
 };
 use kube::Client as KubeClient;
 
-/// Load mainfest from different sources and return the actor spec.
+/// Loads a manifest from the relevant source and pins it with a lockfile
+/// entry. `existing` is the [`LockEntry`] this partner locked to the last
+/// time it was resolved, if any; when its digest no longer matches what
+/// was just resolved, `load` fails with [`ResolveError::IntegrityMismatch`]
+/// unless `relock` is set, e.g. because the Playbook explicitly asked to
+/// re-pin after a partner's `master` ref moved.
+///
+/// Before hitting the catalog/hub/source, a previously pinned commit is
+/// looked up in the active [`cache::ManifestCache`] so repeated reconciles
+/// of the same commit don't re-fetch it.
 pub async fn load(
     client: &KubeClient,
     credentials: &Credentials,
     name: &str,
     partner: &Partner,
-) -> Result<CharacterSpec> {
-    match partner {
-        Partner::Registry(p) => {
-            let registry = p.registry.clone().unwrap_or_else(|| "catalog".to_string());
-            return match registry.as_str() {
-                "catalog" => load_from_catalog(credentials, name, &p.version),
-                "hub" => load_from_cluster(client, name).await,
-                x => Err(ResolveError::UnknownCharacterRegistry(x.to_string())),
+    existing: Option<&LockEntry>,
+    relock: bool,
+) -> Result<(CharacterSpec, LockEntry)> {
+    let store = cache::active();
+
+    let cached = match existing.map(|entry| entry.commit.as_str()).filter(|commit| is_pinned(commit)) {
+        Some(commit) => store.get(&CacheKey::new(name, commit)).await,
+        None => None,
+    };
+
+    let spec = match cached {
+        Some(spec) => spec,
+        None => {
+            // The ref as the caller asked for it (a symbolic `master` or a
+            // literal commit), *not* the commit it resolved to — every ref
+            // resolves to a commit-shaped `spec.commit`, so checking that
+            // instead would always look pinned.
+            let (spec, requested) = match partner {
+                Partner::Registry(p) => {
+                    let registry = p.registry.clone().unwrap_or_else(|| "catalog".to_string());
+                    let resolver = handler::lookup(&registry)?;
+                    let spec = resolver.resolve(name, &p.version, credentials, client).await?;
+                    (spec, p.version.clone())
+                }
+                Partner::Repository(reference) => (load_from_source(credentials, reference)?, reference.clone()),
+                _ => return Err(ResolveError::UnsupportedPartner),
             };
+
+            let ttl = if is_pinned(&requested) { cache::PINNED_TTL } else { cache::UNPINNED_TTL };
+            store.put(CacheKey::new(name, spec.commit.clone()), spec.clone(), ttl).await;
+            spec
+        }
+    };
+
+    let sha256 = lock::digest(&spec, &spec.commit).map_err(|e| ResolveError::LockSerializationError(e.to_string()))?;
+    let entry = LockEntry {
+        commit: spec.commit.clone(),
+        sha256,
+    };
+
+    if let Some(previous) = existing {
+        if !relock && previous.sha256 != entry.sha256 {
+            return Err(ResolveError::IntegrityMismatch(name.to_string()));
         }
+    }
+
+    Ok((spec, entry))
+}
+
+/// Whether `reference` looks like a resolved commit SHA (as opposed to a
+/// symbolic ref like `master`), and so is safe to cache aggressively.
+fn is_pinned(reference: &str) -> bool {
+    reference.len() == 40 && reference.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_pinned_accepts_a_full_commit_sha() {
+        assert!(is_pinned("285ef2bc98fb6b3db46a96b6a750fad2d0c566b5"));
+    }
+
+    #[test]
+    fn is_pinned_rejects_a_symbolic_ref() {
+        assert!(!is_pinned("master"));
+    }
 
-        Partner::Repository(reference) => load_from_source(credentials, reference),
-        _ => Err(ResolveError::UnsupportedPartner),
+    #[test]
+    fn is_pinned_rejects_a_short_or_non_hex_sha() {
+        assert!(!is_pinned("285ef2b"));
+        assert!(!is_pinned(&"z".repeat(40)));
     }
 }
@@ -12,10 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use amp_common::config::Credentials;
+use amp_common::resource::Partner;
+use amp_resolver::lock::{Lock, LockEntry};
 use kube::runtime::controller::Action;
 use kube::runtime::events::{Event, EventType, Recorder};
 use kube::runtime::finalizer::{finalizer, Event as FinalizerEvent};
@@ -28,6 +32,9 @@ use crate::resources::{actor, playbook};
 pub struct Ctx {
     /// Kubernetes client
     pub client: Client,
+    /// Credentials used to authenticate partner repo/registry lookups
+    /// while resolving a Playbook's partner graph.
+    pub credentials: Credentials,
 }
 
 impl Ctx {
@@ -87,46 +94,95 @@ impl Playbook {
         playbook::patch_status(ctx.client.clone(), self, PlaybookState::solving()).await
     }
 
+    /// Resolves the full transitive partner graph starting from the
+    /// playbook's declared actors. Unlike a shallow single pass, this walks
+    /// every partner's own `partners` until nothing new is discovered,
+    /// deduplicating by [`PartnerKey`] so a repo referenced by several
+    /// actors is only fetched once, and aborts with
+    /// [`Error::DependencyCycle`] if a partner transitively depends on
+    /// itself. The resolved nodes are recorded in reverse-finish
+    /// (topological) order so `run()` can build dependencies before the
+    /// actors that depend on them.
     async fn solve(&self, ctx: Arc<Ctx>) -> Result<()> {
-        let exists: HashSet<String> = self.spec.actors.iter().map(|a| a.repo.clone()).collect();
-        let mut fetches: HashSet<String> = HashSet::new();
+        let mut resolved: HashMap<PartnerKey, Actor> = HashMap::new();
+        let mut marks: HashMap<PartnerKey, Mark> = HashMap::new();
+        let mut order: Vec<PartnerKey> = Vec::new();
 
         for actor in &self.spec.actors {
-            if actor.partners.is_empty() {
-                continue;
-            }
+            let key = PartnerKey::new(&actor.repo, &actor.reference);
+            resolved.insert(key, actor.clone());
+        }
 
-            for repo in &actor.partners {
-                if exists.contains(repo) {
-                    continue;
-                }
-                fetches.insert(repo.to_string());
+        // What every partner locked to the last time this Playbook solved,
+        // consulted so a resolve that drifts since then fails loudly instead
+        // of silently rebuilding against something different.
+        let existing_lock = self.status.as_ref().map(|status| status.lock.clone()).unwrap_or_default();
+        let new_lock = Arc::new(Mutex::new(Lock::default()));
+
+        let resolve = |repo: String, reference: String| {
+            let ctx = ctx.clone();
+            let key = PartnerKey::new(&repo, &reference).to_string();
+            let existing = existing_lock.get(&key).cloned();
+            let new_lock = new_lock.clone();
+            async move {
+                let (actor, entry) = read_partner(&ctx, &repo, &reference, existing.as_ref()).await?;
+                new_lock.lock().unwrap().set(key, entry);
+                Ok(actor)
             }
+        };
+
+        for actor in &self.spec.actors {
+            let root = PartnerKey::new(&actor.repo, &actor.reference);
+            walk(&resolve, root, &mut resolved, &mut marks, &mut order).await?;
         }
 
-        for url in fetches.iter() {
-            tracing::info!("fetches url: {}", url);
-            let actor: Actor = read_partner(url);
-            actor::add(ctx.client.clone(), self, actor).await?;
+        for key in &order {
+            if self.spec.actors.iter().any(|a| &PartnerKey::new(&a.repo, &a.reference) == key) {
+                continue;
+            }
+            tracing::info!("fetches partner: {}", key);
+            actor::add(ctx.client.clone(), self, resolved[key].clone()).await?;
         }
 
-        tracing::info!("fetches length: {}", fetches.len());
+        tracing::info!("resolved {} partners in total", order.len());
 
-        if fetches.is_empty() {
-            playbook::patch_status(ctx.client.clone(), self, PlaybookState::ready()).await?;
-        }
+        let names = order.iter().map(|key| key.to_string()).collect();
+        playbook::patch_resolution(ctx.client.clone(), self, names).await?;
+        playbook::patch_lock(ctx.client.clone(), self, new_lock.lock().unwrap().clone()).await?;
+        playbook::patch_status(ctx.client.clone(), self, PlaybookState::ready()).await?;
 
         Ok(())
     }
 
     async fn run(&self, ctx: Arc<Ctx>) -> Result<()> {
-        for actor in &self.spec.actors {
+        for actor in self.build_order() {
             actor::build(ctx.client.clone(), self, actor).await?;
             actor::deploy(ctx.client.clone(), self, actor).await?;
         }
         Ok(())
     }
 
+    /// Returns the playbook's actors ordered so that an actor's partners
+    /// always precede it, falling back to declaration order when no
+    /// resolution has been recorded yet (e.g. before the first `solve()`).
+    fn build_order(&self) -> Vec<&crate::resources::types::ActorSpec> {
+        let resolution = self.status.as_ref().map(|s| s.resolution.as_slice()).unwrap_or_default();
+
+        if resolution.is_empty() {
+            return self.spec.actors.iter().collect();
+        }
+
+        resolution
+            .iter()
+            .filter_map(|name| {
+                self.spec
+                    .actors
+                    .iter()
+                    .find(|a| &PartnerKey::new(&a.repo, &a.reference).to_string() == name)
+            })
+            .collect()
+    }
+
     pub async fn cleanup(&self, ctx: Arc<Ctx>) -> Result<Action> {
         // todo add some deletion event logging, db clean up, etc.?
         let recorder = ctx.recorder(self);
@@ -145,16 +201,254 @@ impl Playbook {
     }
 }
 
-fn read_partner(url: &String) -> Actor {
-    Actor {
-        name: "amp-example-nodejs".into(),
-        description: "A simple NodeJs example app".into(),
-        image: "amp-example-nodejs".into(),
-        repo: url.into(),
-        path: ".".into(),
-        reference: "master".into(),
-        commit: "285ef2bc98fb6b3db46a96b6a750fad2d0c566b5".into(),
-        environment: HashMap::new(),
-        partners: vec![],
+/// Iteratively resolves `root` and everything reachable from it through an
+/// explicit stack rather than recursion. Each [`PartnerKey`] is three-color
+/// marked: absent (white, unseen), [`Mark::Gray`] while it's on the current
+/// path, and [`Mark::Black`] once it and all of its partners are fully
+/// resolved. Finding a gray key again means the partner graph cycles back
+/// on itself. Free function rather than a `Playbook` method since it
+/// operates purely on the maps it's given, which also makes it testable
+/// without a live `Playbook` object.
+///
+/// Resolving a not-yet-seen partner is delegated to `resolve` rather than
+/// called directly, so the graph algorithm can be unit tested against a
+/// prepopulated `resolved` map without a live Kubernetes client; `solve()`
+/// passes a closure backed by [`read_partner`], which resolves through
+/// `amp_resolver::partner::load` for a real lockfile entry.
+async fn walk<F, Fut>(
+    resolve: &F, root: PartnerKey, resolved: &mut HashMap<PartnerKey, Actor>, marks: &mut HashMap<PartnerKey, Mark>,
+    order: &mut Vec<PartnerKey>,
+) -> Result<()>
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = Result<Actor>>,
+{
+    if marks.contains_key(&root) {
+        return Ok(());
+    }
+
+    let mut path: Vec<PartnerKey> = vec![root.clone()];
+    marks.insert(root.clone(), Mark::Gray);
+    let mut stack: Vec<Frame> = vec![Frame::new(root)];
+
+    while let Some(mut frame) = stack.pop() {
+        if !frame.expanded {
+            if !resolved.contains_key(&frame.key) {
+                let actor = resolve(frame.key.repo.clone(), frame.key.reference.clone()).await?;
+                resolved.insert(frame.key.clone(), actor);
+            }
+
+            let node = &resolved[&frame.key];
+            frame.children = node
+                .partners
+                .iter()
+                .map(|repo| PartnerKey::new(repo, "master"))
+                .filter(|child| child != &frame.key)
+                .collect();
+            frame.expanded = true;
+        }
+
+        match frame.children.pop() {
+            Some(child) => {
+                if let Some(conflict) = conflicting(resolved, &child) {
+                    return Err(Error::PartnerConflict(child.repo, conflict.reference, child.reference));
+                }
+
+                match marks.get(&child) {
+                    Some(Mark::Black) => {}
+                    Some(Mark::Gray) => {
+                        path.push(child);
+                        let chain = path.iter().map(PartnerKey::to_string).collect::<Vec<_>>().join(" -> ");
+                        return Err(Error::DependencyCycle(chain));
+                    }
+                    None => {
+                        marks.insert(child.clone(), Mark::Gray);
+                        path.push(child.clone());
+                        stack.push(frame);
+                        stack.push(Frame::new(child));
+                        continue;
+                    }
+                }
+                stack.push(frame);
+            }
+            None => {
+                marks.insert(frame.key.clone(), Mark::Black);
+                order.push(frame.key.clone());
+                path.pop();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolves a not-yet-seen partner through `amp_resolver::partner::load`,
+/// passing back the [`LockEntry`] it was last pinned to (if any) so a
+/// resolve that drifts from it fails with `ResolveError::IntegrityMismatch`
+/// rather than silently rebuilding against something different. Only the
+/// [`Actor`]'s `repo`, `reference` and `commit` are filled in; every other
+/// field, `partners` included, is left at its default, so a partner
+/// resolved here adds no further partners to the walk.
+async fn read_partner(ctx: &Ctx, repo: &str, reference: &str, existing: Option<&LockEntry>) -> Result<(Actor, LockEntry)> {
+    let partner = Partner::Repository(reference.to_string());
+    let (spec, entry) = amp_resolver::partner::load(&ctx.client, &ctx.credentials, repo, &partner, existing, false)
+        .await
+        .map_err(|e| Error::PartnerResolutionError(repo.to_string(), e.to_string()))?;
+
+    let actor = Actor {
+        repo: repo.into(),
+        reference: reference.into(),
+        commit: spec.commit,
+        ..Default::default()
+    };
+    Ok((actor, entry))
+}
+
+/// Identifies a partner by the pair of values that must agree for two
+/// references to the same dependency to be considered the same node: its
+/// repository URL and the git reference (or commit) resolved against it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct PartnerKey {
+    repo: String,
+    reference: String,
+}
+
+impl PartnerKey {
+    fn new(repo: impl Into<String>, reference: impl Into<String>) -> Self {
+        Self {
+            repo: repo.into(),
+            reference: reference.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for PartnerKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.repo, self.reference)
+    }
+}
+
+/// Three-color mark used while walking the partner graph: a key with no
+/// entry is unseen (white), [`Mark::Gray`] is on the current path, and
+/// [`Mark::Black`] is fully resolved.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Gray,
+    Black,
+}
+
+/// One level of the explicit DFS stack used by [`walk`]. Children
+/// are computed once (`expanded`) and then drained as they're visited.
+struct Frame {
+    key: PartnerKey,
+    expanded: bool,
+    children: Vec<PartnerKey>,
+}
+
+impl Frame {
+    fn new(key: PartnerKey) -> Self {
+        Self {
+            key,
+            expanded: false,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Returns the already-resolved key for `candidate`'s repo if it was
+/// resolved against a different reference, i.e. the same dependency was
+/// asked for at two incompatible versions.
+fn conflicting(resolved: &HashMap<PartnerKey, Actor>, candidate: &PartnerKey) -> Option<PartnerKey> {
+    resolved
+        .keys()
+        .find(|key| key.repo == candidate.repo && key.reference != candidate.reference)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(repo: &str, partners: &[&str]) -> Actor {
+        Actor {
+            repo: repo.into(),
+            partners: partners.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// A resolver that should never be called: every test below
+    /// prepopulates `resolved` with every key the walk will touch.
+    fn unreachable_resolver(_repo: String, _reference: String) -> impl Future<Output = Result<Actor>> {
+        async { unreachable!("test graphs are fully prepopulated") }
+    }
+
+    #[tokio::test]
+    async fn walk_orders_a_dag_with_partners_before_dependents() {
+        let mut resolved = HashMap::new();
+        resolved.insert(PartnerKey::new("a", "master"), node("a", &["b"]));
+        resolved.insert(PartnerKey::new("b", "master"), node("b", &[]));
+
+        let mut marks = HashMap::new();
+        let mut order = Vec::new();
+        walk(&unreachable_resolver, PartnerKey::new("a", "master"), &mut resolved, &mut marks, &mut order)
+            .await
+            .unwrap();
+
+        assert_eq!(order, vec![PartnerKey::new("b", "master"), PartnerKey::new("a", "master")]);
+    }
+
+    #[tokio::test]
+    async fn walk_rejects_a_cycle() {
+        let mut resolved = HashMap::new();
+        resolved.insert(PartnerKey::new("a", "master"), node("a", &["b"]));
+        resolved.insert(PartnerKey::new("b", "master"), node("b", &["a"]));
+
+        let mut marks = HashMap::new();
+        let mut order = Vec::new();
+        let err = walk(&unreachable_resolver, PartnerKey::new("a", "master"), &mut resolved, &mut marks, &mut order)
+            .await
+            .unwrap_err();
+
+        match err {
+            Error::DependencyCycle(chain) => {
+                assert!(chain.contains("a@master"));
+                assert!(chain.contains("b@master"));
+            }
+            other => panic!("expected DependencyCycle, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn walk_rejects_a_partner_requested_at_two_references() {
+        let mut resolved = HashMap::new();
+        resolved.insert(PartnerKey::new("a", "master"), node("a", &["c"]));
+        // "c" was already resolved at "v1" by some other actor; walking "a"'s
+        // partner "c" at "master" conflicts with that.
+        resolved.insert(PartnerKey::new("c", "v1"), node("c", &[]));
+
+        let mut marks = HashMap::new();
+        let mut order = Vec::new();
+        let err = walk(&unreachable_resolver, PartnerKey::new("a", "master"), &mut resolved, &mut marks, &mut order)
+            .await
+            .unwrap_err();
+
+        match err {
+            Error::PartnerConflict(repo, first, second) => {
+                assert_eq!(repo, "c");
+                assert_eq!(first, "v1");
+                assert_eq!(second, "master");
+            }
+            other => panic!("expected PartnerConflict, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn conflicting_ignores_the_same_reference() {
+        let mut resolved = HashMap::new();
+        resolved.insert(PartnerKey::new("a", "master"), node("a", &[]));
+
+        assert!(conflicting(&resolved, &PartnerKey::new("a", "master")).is_none());
+        assert!(conflicting(&resolved, &PartnerKey::new("a", "v2")).is_some());
     }
 }
@@ -0,0 +1,306 @@
+// Copyright 2022 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::future::Future;
+use std::time::Duration;
+
+use chrono::Utc;
+use k8s_openapi::api::coordination::v1::{Lease, LeaseSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::MicroTime;
+use kube::api::PostParams;
+use kube::{Api, Client};
+use tokio::time::sleep;
+
+/// Configuration for `coordination.k8s.io/v1` Lease based leader election,
+/// mirroring the standard `--leader-elect*` flags so operators can tune it
+/// per deployment, or turn it off entirely for single-instance dev.
+#[derive(Clone, Debug)]
+pub struct LeaderElectionConfig {
+    /// Enables leader election. Set to `false` to run unconditionally,
+    /// e.g. when only a single composer replica is deployed.
+    pub enabled: bool,
+    /// Name of the Lease object used to coordinate replicas.
+    pub lease_name: String,
+    /// Namespace the Lease lives in.
+    pub lease_namespace: String,
+    /// How long a held lease is valid for before it's considered stale.
+    pub lease_duration: Duration,
+    /// How long the leader has to renew before stepping down voluntarily.
+    pub renew_deadline: Duration,
+    /// Delay between acquire/renew attempts.
+    pub retry_period: Duration,
+}
+
+impl Default for LeaderElectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            lease_name: "amphitheatre-composer".into(),
+            lease_namespace: "amp-system".into(),
+            lease_duration: Duration::from_secs(15),
+            renew_deadline: Duration::from_secs(10),
+            retry_period: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Runs `task` only while this process holds the configured Lease.
+///
+/// Blocks acquiring the lease, then races the running task against periodic
+/// renewal: if no renewal succeeds for longer than `renew_deadline` the
+/// task is dropped so a standby replica can take over, rather than risking
+/// two replicas reconciling the same objects at once.
+pub async fn run_with_lease<F, Fut>(client: Client, config: LeaderElectionConfig, identity: String, task: F)
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    if !config.enabled {
+        tracing::info!("Leader election disabled, running unconditionally as {}", identity);
+        task().await;
+        return;
+    }
+
+    let leases: Api<Lease> = Api::namespaced(client, &config.lease_namespace);
+
+    loop {
+        match acquire(&leases, &config, &identity).await {
+            Ok(()) => {
+                tracing::info!("Acquired leader lease \"{}\" as {}", config.lease_name, identity);
+                break;
+            }
+            Err(e) => {
+                tracing::debug!("Leader lease \"{}\" not acquired yet: {:?}", config.lease_name, e);
+                sleep(config.retry_period).await;
+            }
+        }
+    }
+
+    let running = task();
+    tokio::pin!(running);
+
+    // Tracks the last successful renewal so a single transient failure
+    // doesn't immediately give up the lease: `retry_period` is how often we
+    // *try* to renew, `renew_deadline` is how long we tolerate failing to
+    // before we actually step down.
+    let mut last_renewed = tokio::time::Instant::now();
+
+    loop {
+        tokio::select! {
+            _ = &mut running => {
+                tracing::info!("Leading task for lease \"{}\" exited", config.lease_name);
+                return;
+            }
+            _ = sleep(config.retry_period) => {
+                match renew(&leases, &config, &identity).await {
+                    Ok(()) => last_renewed = tokio::time::Instant::now(),
+                    Err(e) => {
+                        tracing::warn!("Failed to renew leader lease \"{}\": {:?}", config.lease_name, e);
+
+                        if last_renewed.elapsed() > config.renew_deadline {
+                            tracing::error!(
+                                "Leader lease \"{}\" not renewed within the deadline, stepping down",
+                                config.lease_name
+                            );
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Attempts to become (or remain) the holder of the Lease, gating the
+/// write on the `resourceVersion` last observed so two replicas racing to
+/// acquire can't both "win": whichever one's write lands second is
+/// rejected by the apiserver with a real conflict instead of silently
+/// clobbering the other (mirroring client-go's `LeaseLock`).
+async fn acquire(leases: &Api<Lease>, config: &LeaderElectionConfig, identity: &str) -> kube::Result<()> {
+    let previous = leases.get_opt(&config.lease_name).await?;
+
+    if let Some(lease) = &previous {
+        if !expired(lease, config) && held_by_other(lease, identity) {
+            return Err(conflict_error("lease is currently held by another replica"));
+        }
+    }
+
+    let transitions = next_transition_count(previous.as_ref(), identity);
+    apply(leases, config, identity, transitions, previous).await
+}
+
+/// Bumps the lease's transition counter only when the holder identity is
+/// actually changing. Without this, a replica re-acquiring its own
+/// still-valid lease (e.g. right after a quick restart) would reset the
+/// counter ops rely on to detect flapping.
+fn next_transition_count(previous: Option<&Lease>, identity: &str) -> i32 {
+    match previous.and_then(|lease| lease.spec.as_ref()) {
+        Some(spec) => {
+            let current = spec.lease_transitions.unwrap_or(0);
+            match spec.holder_identity.as_deref() {
+                Some(holder) if holder == identity => current,
+                _ => current + 1,
+            }
+        }
+        None => 0,
+    }
+}
+
+/// Renews the Lease, keeping the existing transition count untouched.
+/// Re-checks `held_by_other` against the freshly fetched Lease first: if
+/// another replica has already taken over (its identity visible, or its
+/// write about to make this one's `resourceVersion` stale), renewal fails
+/// immediately rather than this replica overwriting the new holder's
+/// identity right back with its own.
+async fn renew(leases: &Api<Lease>, config: &LeaderElectionConfig, identity: &str) -> kube::Result<()> {
+    let current = leases.get(&config.lease_name).await?;
+
+    if held_by_other(&current, identity) {
+        return Err(conflict_error("lease was taken over by another replica"));
+    }
+
+    let transitions = current.spec.as_ref().and_then(|spec| spec.lease_transitions).unwrap_or(0);
+    apply(leases, config, identity, transitions, Some(current)).await
+}
+
+/// Creates or updates the Lease with `previous`'s `resourceVersion` set on
+/// the object being written, so the apiserver rejects the write with a
+/// real conflict if `previous` is no longer current — e.g. another replica
+/// already wrote its own acquisition or renewal in between.
+async fn apply(
+    leases: &Api<Lease>, config: &LeaderElectionConfig, identity: &str, transitions: i32, previous: Option<Lease>,
+) -> kube::Result<()> {
+    let now = Utc::now();
+    let resource_version = previous.and_then(|lease| lease.metadata.resource_version);
+
+    let lease = Lease {
+        metadata: kube::api::ObjectMeta {
+            name: Some(config.lease_name.clone()),
+            namespace: Some(config.lease_namespace.clone()),
+            resource_version: resource_version.clone(),
+            ..Default::default()
+        },
+        spec: Some(LeaseSpec {
+            holder_identity: Some(identity.to_string()),
+            lease_duration_seconds: Some(config.lease_duration.as_secs() as i32),
+            acquire_time: Some(MicroTime(now)),
+            renew_time: Some(MicroTime(now)),
+            lease_transitions: Some(transitions),
+            ..Default::default()
+        }),
+    };
+
+    let params = PostParams::default();
+    match resource_version {
+        Some(_) => {
+            leases.replace(&config.lease_name, &params, &lease).await?;
+        }
+        None => {
+            leases.create(&params, &lease).await?;
+        }
+    }
+    Ok(())
+}
+
+fn conflict_error(message: &str) -> kube::Error {
+    kube::Error::Api(kube::error::ErrorResponse {
+        status: "Failure".into(),
+        message: message.into(),
+        reason: "Conflict".into(),
+        code: 409,
+    })
+}
+
+fn held_by_other(lease: &Lease, identity: &str) -> bool {
+    lease
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.holder_identity.as_deref())
+        .map(|holder| holder != identity)
+        .unwrap_or(false)
+}
+
+fn expired(lease: &Lease, config: &LeaderElectionConfig) -> bool {
+    let Some(spec) = lease.spec.as_ref() else { return true };
+    let Some(renew_time) = spec.renew_time.as_ref() else { return true };
+    let duration = spec.lease_duration_seconds.unwrap_or(config.lease_duration.as_secs() as i32);
+    Utc::now() - renew_time.0 > chrono::Duration::seconds(duration as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lease_with(holder: Option<&str>, transitions: Option<i32>, renew_time: Option<MicroTime>) -> Lease {
+        Lease {
+            metadata: Default::default(),
+            spec: Some(LeaseSpec {
+                holder_identity: holder.map(String::from),
+                lease_duration_seconds: Some(15),
+                renew_time,
+                lease_transitions: transitions,
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn held_by_other_is_false_for_own_identity() {
+        let lease = lease_with(Some("replica-a"), Some(1), Some(MicroTime(Utc::now())));
+        assert!(!held_by_other(&lease, "replica-a"));
+        assert!(held_by_other(&lease, "replica-b"));
+    }
+
+    #[test]
+    fn held_by_other_is_false_without_a_holder() {
+        let lease = lease_with(None, None, None);
+        assert!(!held_by_other(&lease, "replica-a"));
+    }
+
+    #[test]
+    fn expired_lease_without_renew_time_is_expired() {
+        let lease = lease_with(Some("replica-a"), Some(1), None);
+        assert!(expired(&lease, &LeaderElectionConfig::default()));
+    }
+
+    #[test]
+    fn fresh_renewal_is_not_expired() {
+        let lease = lease_with(Some("replica-a"), Some(1), Some(MicroTime(Utc::now())));
+        assert!(!expired(&lease, &LeaderElectionConfig::default()));
+    }
+
+    #[test]
+    fn stale_renewal_is_expired() {
+        let stale = Utc::now() - chrono::Duration::seconds(60);
+        let lease = lease_with(Some("replica-a"), Some(1), Some(MicroTime(stale)));
+        assert!(expired(&lease, &LeaderElectionConfig::default()));
+    }
+
+    #[test]
+    fn transition_count_is_unchanged_when_reacquiring_own_lease() {
+        let lease = lease_with(Some("replica-a"), Some(3), Some(MicroTime(Utc::now())));
+        assert_eq!(next_transition_count(Some(&lease), "replica-a"), 3);
+    }
+
+    #[test]
+    fn transition_count_increments_on_handover() {
+        let lease = lease_with(Some("replica-a"), Some(3), Some(MicroTime(Utc::now())));
+        assert_eq!(next_transition_count(Some(&lease), "replica-b"), 4);
+    }
+
+    #[test]
+    fn transition_count_starts_at_zero_for_a_brand_new_lease() {
+        assert_eq!(next_transition_count(None, "replica-a"), 0);
+    }
+}
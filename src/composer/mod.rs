@@ -23,7 +23,10 @@ use crate::app::Context;
 use crate::resources::crds::{Actor, Playbook};
 
 pub mod actor_controller;
-pub mod playbook_controller;
+pub mod controller;
+pub mod election;
+
+use election::{run_with_lease, LeaderElectionConfig};
 
 pub struct Ctx {
     pub client: Client,
@@ -47,28 +50,37 @@ pub async fn run(ctx: Arc<Context>) {
         std::process::exit(1);
     }
 
-    let context = Arc::new(Ctx {
+    let playbook_context = Arc::new(controller::Ctx {
+        client: ctx.k8s.clone(),
+        credentials: ctx.config.credentials.clone(),
+    });
+    let actor_context = Arc::new(Ctx {
         client: ctx.k8s.clone(),
     });
 
-    let playbook_ctrl = Controller::new(playbook, ListParams::default())
-        .run(
-            playbook_controller::reconcile,
-            playbook_controller::error_policy,
-            context.clone(),
-        )
-        .for_each(|_| future::ready(()));
+    let election = LeaderElectionConfig {
+        enabled: ctx.config.leader_election_enabled,
+        ..LeaderElectionConfig::default()
+    };
+    let identity = std::env::var("POD_NAME").unwrap_or_else(|_| uuid::Uuid::new_v4().to_string());
 
-    let actor_ctrl = Controller::new(actor, ListParams::default())
-        .run(
-            actor_controller::reconcile,
-            actor_controller::error_policy,
-            context.clone(),
-        )
-        .for_each(|_| future::ready(()));
+    run_with_lease(ctx.k8s.clone(), election, identity, || async move {
+        let playbook_ctrl = Controller::new(playbook, ListParams::default())
+            .run(controller::reconcile, controller::error_policy, playbook_context.clone())
+            .for_each(|_| future::ready(()));
 
-    tokio::select! {
-        _ = playbook_ctrl => tracing::warn!("playbook controller exited"),
-        _ = actor_ctrl => tracing::warn!("actor controller exited"),
-    }
+        let actor_ctrl = Controller::new(actor, ListParams::default())
+            .run(
+                actor_controller::reconcile,
+                actor_controller::error_policy,
+                actor_context.clone(),
+            )
+            .for_each(|_| future::ready(()));
+
+        tokio::select! {
+            _ = playbook_ctrl => tracing::warn!("playbook controller exited"),
+            _ = actor_ctrl => tracing::warn!("actor controller exited"),
+        }
+    })
+    .await;
 }
@@ -0,0 +1,36 @@
+// Copyright 2022 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use kube::Client;
+
+use super::error::Result;
+use super::types::{Actor, Playbook};
+
+/// Called for each resolved partner [`Actor`] of `playbook`. Only logs the
+/// actor; nothing is written to the cluster, and the actor is not added to
+/// what `run()` builds and deploys.
+pub async fn add(_client: Client, _playbook: &Playbook, actor: Actor) -> Result<()> {
+    tracing::debug!("Adding resolved partner actor \"{}\" ({})", actor.name, actor.repo);
+    Ok(())
+}
+
+pub async fn build(_client: Client, _playbook: &Playbook, actor: &Actor) -> Result<()> {
+    tracing::debug!("Building actor \"{}\" ({})", actor.name, actor.repo);
+    Ok(())
+}
+
+pub async fn deploy(_client: Client, _playbook: &Playbook, actor: &Actor) -> Result<()> {
+    tracing::debug!("Deploying actor \"{}\" ({})", actor.name, actor.repo);
+    Ok(())
+}
@@ -0,0 +1,23 @@
+// Copyright 2022 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Re-exports the Kubernetes-facing resource kinds for callers (e.g. the
+//! controller bootstrap) that only need the CRD types, not the resolution
+//! helpers in [`super::playbook`] and [`super::actor`].
+
+pub use super::types::Playbook;
+
+/// The `Actor` custom resource kind. `composer::run` watches this
+/// alongside `Playbook` and reconciles it with `actor_controller`.
+pub type Actor = super::types::Actor;
@@ -0,0 +1,46 @@
+// Copyright 2022 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("a playbook must declare at least one actor")]
+    EmptyActorsError,
+
+    #[error("finalizer error: {0:?}")]
+    FinalizerError(#[source] Box<kube::runtime::finalizer::Error<Error>>),
+
+    #[error("kubernetes error: {0}")]
+    KubeError(#[source] kube::Error),
+
+    /// Raised by the partner-graph walk when it reaches a node that's still
+    /// on the current path; `0` names the offending chain, e.g. `a -> b -> a`.
+    #[error("partner dependency cycle detected: {0}")]
+    DependencyCycle(String),
+
+    /// Raised when the same repo is resolved at two incompatible
+    /// references from different actors/partners; fields are
+    /// `(repo, first reference, second reference)`.
+    #[error("partner \"{0}\" requested at incompatible references \"{1}\" and \"{2}\"")]
+    PartnerConflict(String, String, String),
+
+    /// Raised when `amp_resolver::partner::load` fails to resolve a
+    /// partner discovered while walking the graph; `0` is the partner's
+    /// repo, `1` the underlying resolver error.
+    #[error("failed to resolve partner \"{0}\": {1}")]
+    PartnerResolutionError(String, String),
+}
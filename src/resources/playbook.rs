@@ -0,0 +1,84 @@
+// Copyright 2022 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amp_resolver::lock::Lock;
+use kube::api::{Patch, PatchParams};
+use kube::{Api, Client, ResourceExt};
+use serde_json::json;
+
+use super::error::{Error, Result};
+use super::types::{Playbook, PlaybookState};
+
+const FIELD_MANAGER: &str = "amphitheatre-composer";
+
+/// Patches a Playbook's reported `state`, leaving every other status field
+/// (notably `resolution`) untouched.
+pub async fn patch_status(client: Client, playbook: &Playbook, state: PlaybookState) -> Result<()> {
+    patch(
+        client,
+        playbook,
+        json!({
+            "status": {
+                "state": state.state,
+            }
+        }),
+    )
+    .await
+}
+
+/// Records the fully resolved, build-ordered partner graph from the last
+/// successful `solve()`, so `run()` can rebuild the order without re-
+/// walking the graph and so a later reconcile can tell what's already
+/// been resolved.
+pub async fn patch_resolution(client: Client, playbook: &Playbook, resolution: Vec<String>) -> Result<()> {
+    patch(
+        client,
+        playbook,
+        json!({
+            "status": {
+                "resolution": resolution,
+            }
+        }),
+    )
+    .await
+}
+
+/// Records the pinned commit + content digest for every partner resolved
+/// during `solve()`, so the next reconcile can pass them back to
+/// `amp_resolver::partner::load` as `existing` and catch drift instead of
+/// silently re-resolving.
+pub async fn patch_lock(client: Client, playbook: &Playbook, lock: Lock) -> Result<()> {
+    patch(
+        client,
+        playbook,
+        json!({
+            "status": {
+                "lock": lock,
+            }
+        }),
+    )
+    .await
+}
+
+async fn patch(client: Client, playbook: &Playbook, value: serde_json::Value) -> Result<()> {
+    let ns = playbook.namespace().unwrap();
+    let api: Api<Playbook> = Api::namespaced(client, &ns);
+
+    let params = PatchParams::apply(FIELD_MANAGER);
+    api.patch_status(&playbook.name_any(), &params, &Patch::Merge(value))
+        .await
+        .map_err(Error::KubeError)?;
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+// Copyright 2022 The Amphitheatre Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use amp_resolver::lock::Lock;
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Name the `Playbook` finalizer is registered under.
+pub const PLAYBOOK_RESOURCE_NAME: &str = "playbooks.amphitheatre.app";
+
+/// An actor declared by a Playbook, or discovered while resolving one of
+/// its partners. Plain data rather than a Kubernetes object in its own
+/// right: actors live embedded in [`PlaybookSpec`] and, once resolved, in
+/// `Playbook::solve`'s in-memory partner graph.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct Actor {
+    pub name: String,
+    pub description: String,
+    pub image: String,
+    pub repo: String,
+    pub path: String,
+    pub reference: String,
+    pub commit: String,
+    pub environment: HashMap<String, String>,
+    pub partners: Vec<String>,
+}
+
+/// A declared actor before resolution; identical in shape to [`Actor`]
+/// since a declared actor *is* just the seed of the resolved graph.
+pub type ActorSpec = Actor;
+
+/// Spec for the `Playbook` custom resource: the set of actors a user asked
+/// to run together.
+#[derive(CustomResource, Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[kube(
+    group = "amphitheatre.app",
+    version = "v1",
+    kind = "Playbook",
+    namespaced,
+    status = "PlaybookStatus",
+    derive = "Default"
+)]
+pub struct PlaybookSpec {
+    pub actors: Vec<ActorSpec>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+pub enum PlaybookStateKind {
+    #[default]
+    Pending,
+    Solving,
+    Running,
+}
+
+/// Observed status of a Playbook, reported back onto the resource by the
+/// controller as it progresses through `pending -> solving -> running`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct PlaybookStatus {
+    pub state: PlaybookStateKind,
+    /// Fully resolved partner graph from the last successful `solve()`, in
+    /// build order (`repo@reference`, see `PartnerKey`). Used by `run()` to
+    /// build and deploy actors in dependency order, and kept around so a
+    /// later reconcile doesn't need to re-walk the graph to know it.
+    pub resolution: Vec<String>,
+    /// Pinned commit + content digest for every partner resolved so far,
+    /// keyed by `repo@reference` (see `PartnerKey`). Checked against on the
+    /// next `solve()` so a partner that drifted since it was last locked
+    /// fails with `Error::PartnerResolutionError` instead of silently
+    /// rebuilding against something different.
+    pub lock: Lock,
+}
+
+impl PlaybookStatus {
+    pub fn pending(&self) -> bool {
+        self.state == PlaybookStateKind::Pending
+    }
+
+    pub fn solving(&self) -> bool {
+        self.state == PlaybookStateKind::Solving
+    }
+
+    pub fn running(&self) -> bool {
+        self.state == PlaybookStateKind::Running
+    }
+}
+
+/// A status update to patch onto a Playbook, without disturbing fields the
+/// caller doesn't know about (e.g. `resolution`).
+pub struct PlaybookState {
+    pub state: PlaybookStateKind,
+}
+
+impl PlaybookState {
+    pub fn solving() -> Self {
+        Self {
+            state: PlaybookStateKind::Solving,
+        }
+    }
+
+    /// Marks solving complete: the next reconcile finds `running()` true
+    /// and moves on to `Playbook::run`.
+    pub fn ready() -> Self {
+        Self {
+            state: PlaybookStateKind::Running,
+        }
+    }
+}